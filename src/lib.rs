@@ -1,4 +1,6 @@
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::convert::TryFrom;
 use std::fmt;
 use std::ops::Deref;
 
@@ -13,6 +15,19 @@ pub enum BitcoinError {
     InvalidFormat,
 }
 
+/// Upper bound on how large a single serialized message is ever trusted to
+/// be. Counts of repeated structures (inputs, outputs, witness items) are
+/// checked against `MAX_PROTOCOL_MESSAGE_LEN / min_serialized_size()` before
+/// being used to preallocate a `Vec`, so a bogus count can't trigger a
+/// multi-gigabyte allocation before the underlying bytes are validated.
+pub const MAX_PROTOCOL_MESSAGE_LEN: usize = 2 * 1024 * 1024;
+
+/// Returns the largest count of `min_size`-byte structures that could
+/// plausibly fit in a `MAX_PROTOCOL_MESSAGE_LEN` message.
+fn max_trusted_count(min_size: usize) -> usize {
+    MAX_PROTOCOL_MESSAGE_LEN / min_size
+}
+
 impl CompactSize {
     pub fn new(value: u64) -> Self {
         // TODO: Construct a CompactSize from a u64 value
@@ -62,6 +77,10 @@ impl CompactSize {
                     return Err(BitcoinError::InsufficientBytes);
                 }
                 let value = u16::from_le_bytes(bytes[1..3].try_into().unwrap()) as u64;
+                // Canonical encoding: 0xFD must not be used for values that fit in a single byte.
+                if value < 0xFD {
+                    return Err(BitcoinError::InvalidFormat);
+                }
                 Ok((CompactSize::new(value), 3))
             }
             0xFE => {
@@ -69,6 +88,10 @@ impl CompactSize {
                     return Err(BitcoinError::InsufficientBytes);
                 }
                 let value = u32::from_le_bytes(bytes[1..5].try_into().unwrap()) as u64;
+                // Canonical encoding: 0xFE must not be used for values that fit in 0xFD form.
+                if value < 0x10000 {
+                    return Err(BitcoinError::InvalidFormat);
+                }
                 Ok((CompactSize::new(value), 5))
             }
             0xFF => {
@@ -76,6 +99,10 @@ impl CompactSize {
                     return Err(BitcoinError::InsufficientBytes);
                 }
                 let value = u64::from_le_bytes(bytes[1..9].try_into().unwrap());
+                // Canonical encoding: 0xFF must not be used for values that fit in 0xFE form.
+                if value < 0x100000000 {
+                    return Err(BitcoinError::InvalidFormat);
+                }
                 Ok((CompactSize::new(value), 9))
             }
             _ => {
@@ -85,6 +112,147 @@ impl CompactSize {
             }
         }
     }
+
+    /// Number of bytes `to_bytes` would produce for this value, without
+    /// actually allocating or encoding anything.
+    pub fn serialized_len(&self) -> usize {
+        match self.value {
+            0..=0xFC => 1,
+            0xFD..=0xFFFF => 3,
+            0x10000..=0xFFFFFFFF => 5,
+            _ => 9,
+        }
+    }
+}
+
+impl From<u8> for CompactSize {
+    fn from(value: u8) -> Self {
+        CompactSize::new(value as u64)
+    }
+}
+
+impl From<u16> for CompactSize {
+    fn from(value: u16) -> Self {
+        CompactSize::new(value as u64)
+    }
+}
+
+impl From<u32> for CompactSize {
+    fn from(value: u32) -> Self {
+        CompactSize::new(value as u64)
+    }
+}
+
+impl From<u64> for CompactSize {
+    fn from(value: u64) -> Self {
+        CompactSize::new(value)
+    }
+}
+
+impl From<usize> for CompactSize {
+    fn from(value: usize) -> Self {
+        CompactSize::new(value as u64)
+    }
+}
+
+impl TryFrom<CompactSize> for u8 {
+    type Error = BitcoinError;
+
+    fn try_from(size: CompactSize) -> Result<Self, Self::Error> {
+        u8::try_from(size.value).map_err(|_| BitcoinError::InvalidFormat)
+    }
+}
+
+impl TryFrom<CompactSize> for u16 {
+    type Error = BitcoinError;
+
+    fn try_from(size: CompactSize) -> Result<Self, Self::Error> {
+        u16::try_from(size.value).map_err(|_| BitcoinError::InvalidFormat)
+    }
+}
+
+impl TryFrom<CompactSize> for u32 {
+    type Error = BitcoinError;
+
+    fn try_from(size: CompactSize) -> Result<Self, Self::Error> {
+        u32::try_from(size.value).map_err(|_| BitcoinError::InvalidFormat)
+    }
+}
+
+impl TryFrom<CompactSize> for usize {
+    type Error = BitcoinError;
+
+    fn try_from(size: CompactSize) -> Result<Self, Self::Error> {
+        usize::try_from(size.value).map_err(|_| BitcoinError::InvalidFormat)
+    }
+}
+
+/// A variable-length integer codec used to prefix repeated structures (e.g.
+/// scripts, inputs) with their length or count. `Script` and
+/// `BitcoinTransaction` are generic over this so the same structs can
+/// serialize either Bitcoin's CompactSize wire format or another chain's.
+pub trait VarInt {
+    fn encode(value: u64) -> Vec<u8>;
+    fn decode(bytes: &[u8]) -> Result<(u64, usize), BitcoinError>;
+}
+
+impl VarInt for CompactSize {
+    fn encode(value: u64) -> Vec<u8> {
+        CompactSize::new(value).to_bytes()
+    }
+
+    fn decode(bytes: &[u8]) -> Result<(u64, usize), BitcoinError> {
+        let (size, len) = CompactSize::from_bytes(bytes)?;
+        Ok((size.value, len))
+    }
+}
+
+/// Solana-style ULEB128 ("shortvec") length prefix: each byte carries 7
+/// value bits in its low bits, with the high bit set to indicate that
+/// another byte follows.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct ShortVec;
+
+impl VarInt for ShortVec {
+    fn encode(value: u64) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        let mut remaining = value;
+        loop {
+            let mut byte = (remaining & 0x7F) as u8;
+            remaining >>= 7;
+            if remaining != 0 {
+                byte |= 0x80;
+            }
+            bytes.push(byte);
+            if remaining == 0 {
+                break;
+            }
+        }
+        bytes
+    }
+
+    fn decode(bytes: &[u8]) -> Result<(u64, usize), BitcoinError> {
+        let mut value: u64 = 0;
+        for (i, &byte) in bytes.iter().enumerate() {
+            if i >= 10 {
+                return Err(BitcoinError::InvalidFormat);
+            }
+            let continues = byte & 0x80 != 0;
+            let payload = (byte & 0x7F) as u64;
+            if !continues {
+                // A zero-valued terminating byte beyond the first position
+                // is a non-minimal encoding: the prior byte could have
+                // terminated the sequence itself.
+                if payload == 0 && i > 0 {
+                    return Err(BitcoinError::InvalidFormat);
+                }
+                value |= payload << (7 * i);
+                return Ok((value, i + 1));
+            }
+            value |= payload << (7 * i);
+        }
+        Err(BitcoinError::InsufficientBytes)
+    }
 }
 
 #[derive(Debug, PartialEq, Eq, Clone)]
@@ -165,16 +333,38 @@ impl Script {
     }
 
     pub fn to_bytes(&self) -> Vec<u8> {
-        let mut result = CompactSize::new(self.bytes.len() as u64).to_bytes();
+        self.to_bytes_with::<CompactSize>()
+    }
+
+    /// Serializes using `V` as the length-prefix codec instead of the
+    /// default CompactSize.
+    pub fn to_bytes_with<V: VarInt>(&self) -> Vec<u8> {
+        let mut prefix = V::encode(self.bytes.len() as u64);
+        let mut result = Vec::with_capacity(prefix.len() + self.bytes.len());
+        result.append(&mut prefix);
         result.extend_from_slice(&self.bytes);
         result
     }
 
     pub fn from_bytes(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
-        let (compact_size, compact_size_len) = CompactSize::from_bytes(bytes)?;
-        let script_len = compact_size.value as usize;
-        let start_index = compact_size_len;
-        let end_index = start_index + script_len;
+        Self::from_bytes_with::<CompactSize>(bytes)
+    }
+
+    /// Decodes using `V` as the length-prefix codec instead of the default
+    /// CompactSize.
+    pub fn from_bytes_with<V: VarInt>(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
+        let (script_len, prefix_len) = V::decode(bytes)?;
+        // A script can never legitimately be anywhere near this large;
+        // reject it up front so the length doesn't overflow `usize` (or the
+        // index arithmetic below) on a crafted 0xFF/huge-value prefix.
+        if script_len > MAX_PROTOCOL_MESSAGE_LEN as u64 {
+            return Err(BitcoinError::InvalidFormat);
+        }
+        let script_len = script_len as usize;
+        let start_index = prefix_len;
+        let end_index = start_index
+            .checked_add(script_len)
+            .ok_or(BitcoinError::InvalidFormat)?;
 
         if bytes.len() < end_index {
             return Err(BitcoinError::InsufficientBytes);
@@ -200,6 +390,12 @@ pub struct TransactionInput {
 }
 
 impl TransactionInput {
+    /// Smallest possible serialized size: a 36-byte `OutPoint`, an empty
+    /// `script_sig` (1 byte of CompactSize length), and a 4-byte sequence.
+    pub const fn min_serialized_size() -> usize {
+        36 + 1 + 4
+    }
+
     pub fn new(previous_output: OutPoint, script_sig: Script, sequence: u32) -> Self {
         TransactionInput {
             previous_output,
@@ -209,15 +405,23 @@ impl TransactionInput {
     }
 
     pub fn to_bytes(&self) -> Vec<u8> {
+        self.to_bytes_with::<CompactSize>()
+    }
+
+    pub fn to_bytes_with<V: VarInt>(&self) -> Vec<u8> {
         let mut bytes = self.previous_output.to_bytes();
-        bytes.extend_from_slice(&self.script_sig.to_bytes());
+        bytes.extend_from_slice(&self.script_sig.to_bytes_with::<V>());
         bytes.extend_from_slice(&self.sequence.to_le_bytes());
         bytes
     }
 
     pub fn from_bytes(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
+        Self::from_bytes_with::<CompactSize>(bytes)
+    }
+
+    pub fn from_bytes_with<V: VarInt>(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
         let (previous_output, outpoint_len) = OutPoint::from_bytes(bytes)?;
-        let (script_sig, script_len) = Script::from_bytes(&bytes[outpoint_len..])?;
+        let (script_sig, script_len) = Script::from_bytes_with::<V>(&bytes[outpoint_len..])?;
         let sequence_start = outpoint_len + script_len;
         let sequence_end = sequence_start + 4;
 
@@ -233,58 +437,301 @@ impl TransactionInput {
     }
 }
 
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct TransactionOutput {
+    pub value: u64,
+    pub script_pubkey: Script,
+}
+
+impl TransactionOutput {
+    /// Smallest possible serialized size: an 8-byte value and an empty
+    /// `script_pubkey` (1 byte of CompactSize length).
+    pub const fn min_serialized_size() -> usize {
+        8 + 1
+    }
+
+    pub fn new(value: u64, script_pubkey: Script) -> Self {
+        TransactionOutput {
+            value,
+            script_pubkey,
+        }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.to_bytes_with::<CompactSize>()
+    }
+
+    pub fn to_bytes_with<V: VarInt>(&self) -> Vec<u8> {
+        let mut bytes = self.value.to_le_bytes().to_vec();
+        bytes.extend_from_slice(&self.script_pubkey.to_bytes_with::<V>());
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
+        Self::from_bytes_with::<CompactSize>(bytes)
+    }
+
+    pub fn from_bytes_with<V: VarInt>(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
+        if bytes.len() < 8 {
+            return Err(BitcoinError::InsufficientBytes);
+        }
+        let value = u64::from_le_bytes(bytes[0..8].try_into().unwrap());
+        let (script_pubkey, script_len) = Script::from_bytes_with::<V>(&bytes[8..])?;
+        Ok((
+            TransactionOutput::new(value, script_pubkey),
+            8 + script_len,
+        ))
+    }
+}
+
+/// A single input's witness stack: an ordered list of byte-string items.
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct Witness(pub Vec<Vec<u8>>);
+
+impl Witness {
+    /// Smallest possible serialized size: an empty stack (1 byte of
+    /// CompactSize item count).
+    pub const fn min_serialized_size() -> usize {
+        1
+    }
+
+    pub fn new(items: Vec<Vec<u8>>) -> Self {
+        Witness(items)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = CompactSize::new(self.0.len() as u64).to_bytes();
+        for item in &self.0 {
+            bytes.extend_from_slice(&CompactSize::new(item.len() as u64).to_bytes());
+            bytes.extend_from_slice(item);
+        }
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
+        let (item_count_compact_size, compact_size_len) = CompactSize::from_bytes(bytes)?;
+        let item_count = item_count_compact_size.value as usize;
+        let mut offset = compact_size_len;
+
+        // `max_trusted_count` alone is far too loose here: each `Vec<u8>`
+        // element costs ~24 bytes in memory but only 1 byte on the wire, so
+        // bounding solely by `MAX_PROTOCOL_MESSAGE_LEN / min_serialized_size`
+        // still lets a handful of input bytes justify a multi-megabyte
+        // `with_capacity` call. Every item needs at least one more byte in
+        // `bytes`, so additionally cap by what's actually left to read.
+        let max_items =
+            max_trusted_count(Self::min_serialized_size()).min(bytes.len() - offset);
+        if item_count > max_items {
+            return Err(BitcoinError::InvalidFormat);
+        }
+        let mut items = Vec::with_capacity(item_count);
+        for _ in 0..item_count {
+            let (item_len_compact_size, item_len_compact_size_len) =
+                CompactSize::from_bytes(&bytes[offset..])?;
+            // Reject up front so a crafted 0xFF/huge-value item length can't
+            // overflow the `offset + item_len` arithmetic below.
+            if item_len_compact_size.value > MAX_PROTOCOL_MESSAGE_LEN as u64 {
+                return Err(BitcoinError::InvalidFormat);
+            }
+            let item_len = item_len_compact_size.value as usize;
+            offset += item_len_compact_size_len;
+
+            let item_end = offset
+                .checked_add(item_len)
+                .ok_or(BitcoinError::InvalidFormat)?;
+            if bytes.len() < item_end {
+                return Err(BitcoinError::InsufficientBytes);
+            }
+            items.push(bytes[offset..item_end].to_vec());
+            offset = item_end;
+        }
+
+        Ok((Witness::new(items), offset))
+    }
+}
+
+/// Marker/flag bytes that precede a SegWit transaction's input count (BIP-144).
+const SEGWIT_MARKER: u8 = 0x00;
+const SEGWIT_FLAG: u8 = 0x01;
+
 #[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
 pub struct BitcoinTransaction {
     pub version: u32,
     pub inputs: Vec<TransactionInput>,
+    pub outputs: Vec<TransactionOutput>,
     pub lock_time: u32,
+    /// One witness stack per input, present only for SegWit transactions.
+    pub witnesses: Vec<Vec<Vec<u8>>>,
 }
 
 impl BitcoinTransaction {
-    pub fn new(version: u32, inputs: Vec<TransactionInput>, lock_time: u32) -> Self {
+    /// Smallest possible serialized size: a 4-byte version, empty input and
+    /// output CompactSize counts (1 byte each), and a 4-byte lock time.
+    pub const fn min_serialized_size() -> usize {
+        4 + 1 + 1 + 4
+    }
+
+    pub fn new(
+        version: u32,
+        inputs: Vec<TransactionInput>,
+        outputs: Vec<TransactionOutput>,
+        lock_time: u32,
+        witnesses: Vec<Vec<Vec<u8>>>,
+    ) -> Self {
         BitcoinTransaction {
             version,
             inputs,
+            outputs,
             lock_time,
+            witnesses,
         }
     }
 
+    /// A transaction is serialized in SegWit form when at least one input
+    /// carries a non-empty witness stack, or when it has zero inputs — a
+    /// zero-input count is otherwise indistinguishable from the marker
+    /// byte, so BIP-144 reserves that encoding for the SegWit form.
+    fn is_segwit(&self) -> bool {
+        self.inputs.is_empty() || self.witnesses.iter().any(|w| !w.is_empty())
+    }
+
     pub fn to_bytes(&self) -> Vec<u8> {
-        let mut bytes = self.version.to_le_bytes().to_vec();
-        bytes.extend_from_slice(&CompactSize::new(self.inputs.len() as u64).to_bytes());
-        for input in &self.inputs {
-            bytes.extend_from_slice(&input.to_bytes());
+        self.to_bytes_with::<CompactSize>()
+    }
+
+    /// Serializes using `V` as the length-prefix codec for input/output
+    /// counts and script lengths instead of the default CompactSize.
+    /// Witness stacks, being SegWit-specific, always use CompactSize.
+    pub fn to_bytes_with<V: VarInt>(&self) -> Vec<u8> {
+        let segwit = self.is_segwit();
+
+        // Encode every sub-component up front so the capacity reserved
+        // below reflects each item's *actual* size (scripts are almost
+        // never empty in practice) rather than its bare minimum, avoiding
+        // repeated reallocation as real transactions are appended.
+        let input_count = CompactSize::from(self.inputs.len());
+        let output_count = CompactSize::from(self.outputs.len());
+        let encoded_inputs: Vec<Vec<u8>> =
+            self.inputs.iter().map(|input| input.to_bytes_with::<V>()).collect();
+        let encoded_outputs: Vec<Vec<u8>> = self
+            .outputs
+            .iter()
+            .map(|output| output.to_bytes_with::<V>())
+            .collect();
+        let encoded_witnesses: Vec<Vec<u8>> = if segwit {
+            self.witnesses
+                .iter()
+                .map(|witness| Witness::new(witness.clone()).to_bytes())
+                .collect()
+        } else {
+            Vec::new()
+        };
+
+        let capacity = 4
+            + if segwit { 2 } else { 0 }
+            + input_count.serialized_len()
+            + encoded_inputs.iter().map(Vec::len).sum::<usize>()
+            + output_count.serialized_len()
+            + encoded_outputs.iter().map(Vec::len).sum::<usize>()
+            + encoded_witnesses.iter().map(Vec::len).sum::<usize>()
+            + 4;
+
+        let mut bytes = Vec::with_capacity(capacity);
+        bytes.extend_from_slice(&self.version.to_le_bytes());
+        if segwit {
+            bytes.push(SEGWIT_MARKER);
+            bytes.push(SEGWIT_FLAG);
+        }
+
+        bytes.extend_from_slice(&V::encode(self.inputs.len() as u64));
+        for encoded in &encoded_inputs {
+            bytes.extend_from_slice(encoded);
+        }
+
+        bytes.extend_from_slice(&V::encode(self.outputs.len() as u64));
+        for encoded in &encoded_outputs {
+            bytes.extend_from_slice(encoded);
+        }
+
+        for encoded in &encoded_witnesses {
+            bytes.extend_from_slice(encoded);
         }
+
         bytes.extend_from_slice(&self.lock_time.to_le_bytes());
         bytes
     }
 
     pub fn from_bytes(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
+        Self::from_bytes_with::<CompactSize>(bytes)
+    }
+
+    /// Decodes using `V` as the length-prefix codec for input/output counts
+    /// and script lengths instead of the default CompactSize. Witness
+    /// stacks, being SegWit-specific, always use CompactSize.
+    pub fn from_bytes_with<V: VarInt>(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
         if bytes.len() < 4 {
             return Err(BitcoinError::InsufficientBytes);
         }
         let version = u32::from_le_bytes(bytes[0..4].try_into().unwrap());
         let mut offset = 4;
 
-        let (input_count_compact_size, compact_size_len) =
-            CompactSize::from_bytes(&bytes[offset..])?;
-        let input_count = input_count_compact_size.value as usize;
-        offset += compact_size_len;
+        let segwit = bytes.len() >= offset + 2
+            && bytes[offset] == SEGWIT_MARKER
+            && bytes[offset + 1] == SEGWIT_FLAG;
+        if segwit {
+            offset += 2;
+        }
+
+        let (input_count, prefix_len) = V::decode(&bytes[offset..])?;
+        let input_count = input_count as usize;
+        offset += prefix_len;
 
+        let max_inputs = max_trusted_count(TransactionInput::min_serialized_size());
+        if input_count > max_inputs {
+            return Err(BitcoinError::InvalidFormat);
+        }
         let mut inputs = Vec::with_capacity(input_count);
         for _ in 0..input_count {
-            let (input, input_len) = TransactionInput::from_bytes(&bytes[offset..])?;
+            let (input, input_len) = TransactionInput::from_bytes_with::<V>(&bytes[offset..])?;
             inputs.push(input);
             offset += input_len;
         }
 
+        let (output_count, prefix_len) = V::decode(&bytes[offset..])?;
+        let output_count = output_count as usize;
+        offset += prefix_len;
+
+        let max_outputs = max_trusted_count(TransactionOutput::min_serialized_size());
+        if output_count > max_outputs {
+            return Err(BitcoinError::InvalidFormat);
+        }
+        let mut outputs = Vec::with_capacity(output_count);
+        for _ in 0..output_count {
+            let (output, output_len) = TransactionOutput::from_bytes_with::<V>(&bytes[offset..])?;
+            outputs.push(output);
+            offset += output_len;
+        }
+
+        let mut witnesses = Vec::new();
+        if segwit {
+            witnesses.reserve(input_count);
+            for _ in 0..input_count {
+                let (witness, witness_len) = Witness::from_bytes(&bytes[offset..])?;
+                witnesses.push(witness.0);
+                offset += witness_len;
+            }
+        }
+
         if bytes.len() < offset + 4 {
             return Err(BitcoinError::InsufficientBytes);
         }
         let lock_time = u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap());
         offset += 4;
 
-        Ok((BitcoinTransaction::new(version, inputs, lock_time), offset))
+        Ok((
+            BitcoinTransaction::new(version, inputs, outputs, lock_time, witnesses),
+            offset,
+        ))
     }
 }
 
@@ -307,6 +754,404 @@ impl fmt::Display for BitcoinTransaction {
             )?;
             writeln!(f, "    Sequence: {}", input.sequence)?;
         }
+        writeln!(f, "  Outputs ({}):", self.outputs.len())?;
+        for output in &self.outputs {
+            writeln!(f, "    Value: {}", output.value)?;
+            writeln!(
+                f,
+                "    Script Pubkey: {}",
+                hex::encode(&output.script_pubkey.bytes)
+            )?;
+        }
         writeln!(f, "  Lock Time: {}", self.lock_time)
     }
 }
+
+/// Double-SHA256, the hash Bitcoin uses for txids and Merkle tree nodes.
+fn double_sha256(data: &[u8]) -> [u8; 32] {
+    let first = Sha256::digest(data);
+    let second = Sha256::digest(first);
+    second.into()
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct BlockHeader {
+    pub version: u32,
+    pub prev_blockhash: [u8; 32],
+    pub merkle_root: [u8; 32],
+    pub time: u32,
+    pub bits: u32,
+    pub nonce: u32,
+}
+
+impl BlockHeader {
+    /// Fixed serialized size of a block header.
+    pub const SIZE: usize = 80;
+
+    pub fn new(
+        version: u32,
+        prev_blockhash: [u8; 32],
+        merkle_root: [u8; 32],
+        time: u32,
+        bits: u32,
+        nonce: u32,
+    ) -> Self {
+        BlockHeader {
+            version,
+            prev_blockhash,
+            merkle_root,
+            time,
+            bits,
+            nonce,
+        }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(Self::SIZE);
+        bytes.extend_from_slice(&self.version.to_le_bytes());
+        bytes.extend_from_slice(&self.prev_blockhash);
+        bytes.extend_from_slice(&self.merkle_root);
+        bytes.extend_from_slice(&self.time.to_le_bytes());
+        bytes.extend_from_slice(&self.bits.to_le_bytes());
+        bytes.extend_from_slice(&self.nonce.to_le_bytes());
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
+        if bytes.len() < Self::SIZE {
+            return Err(BitcoinError::InsufficientBytes);
+        }
+        let version = u32::from_le_bytes(bytes[0..4].try_into().unwrap());
+        let mut prev_blockhash = [0u8; 32];
+        prev_blockhash.copy_from_slice(&bytes[4..36]);
+        let mut merkle_root = [0u8; 32];
+        merkle_root.copy_from_slice(&bytes[36..68]);
+        let time = u32::from_le_bytes(bytes[68..72].try_into().unwrap());
+        let bits = u32::from_le_bytes(bytes[72..76].try_into().unwrap());
+        let nonce = u32::from_le_bytes(bytes[76..80].try_into().unwrap());
+
+        Ok((
+            BlockHeader::new(version, prev_blockhash, merkle_root, time, bits, nonce),
+            Self::SIZE,
+        ))
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct Block {
+    pub header: BlockHeader,
+    pub transactions: Vec<BitcoinTransaction>,
+}
+
+impl Block {
+    pub fn new(header: BlockHeader, transactions: Vec<BitcoinTransaction>) -> Self {
+        Block {
+            header,
+            transactions,
+        }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.header.to_bytes();
+        bytes.extend_from_slice(&CompactSize::from(self.transactions.len()).to_bytes());
+        for transaction in &self.transactions {
+            bytes.extend_from_slice(&transaction.to_bytes());
+        }
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
+        let (header, header_len) = BlockHeader::from_bytes(bytes)?;
+        let mut offset = header_len;
+
+        let (tx_count_compact_size, compact_size_len) = CompactSize::from_bytes(&bytes[offset..])?;
+        let tx_count = tx_count_compact_size.value as usize;
+        offset += compact_size_len;
+
+        let max_transactions = max_trusted_count(BitcoinTransaction::min_serialized_size());
+        if tx_count > max_transactions {
+            return Err(BitcoinError::InvalidFormat);
+        }
+
+        let mut transactions = Vec::with_capacity(tx_count);
+        for _ in 0..tx_count {
+            let (transaction, transaction_len) = BitcoinTransaction::from_bytes(&bytes[offset..])?;
+            transactions.push(transaction);
+            offset += transaction_len;
+        }
+
+        Ok((Block::new(header, transactions), offset))
+    }
+
+    /// Double-SHA256s each transaction, then repeatedly pairs and
+    /// double-SHA256s adjacent hashes (duplicating the last one when a
+    /// level has an odd count) until a single root hash remains.
+    pub fn compute_merkle_root(&self) -> [u8; 32] {
+        let mut level: Vec<[u8; 32]> = self
+            .transactions
+            .iter()
+            .map(|transaction| double_sha256(&transaction.to_bytes()))
+            .collect();
+
+        if level.is_empty() {
+            return [0u8; 32];
+        }
+
+        while level.len() > 1 {
+            if level.len() % 2 == 1 {
+                level.push(*level.last().unwrap());
+            }
+            level = level
+                .chunks(2)
+                .map(|pair| {
+                    let mut concatenated = Vec::with_capacity(64);
+                    concatenated.extend_from_slice(&pair[0]);
+                    concatenated.extend_from_slice(&pair[1]);
+                    double_sha256(&concatenated)
+                })
+                .collect();
+        }
+
+        level[0]
+    }
+
+    pub fn verify_merkle_root(&self) -> bool {
+        self.compute_merkle_root() == self.header.merkle_root
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compact_size_rejects_non_minimal_0xfd() {
+        let bytes = [0xFD, 0x05, 0x00];
+        assert_eq!(
+            CompactSize::from_bytes(&bytes),
+            Err(BitcoinError::InvalidFormat)
+        );
+    }
+
+    #[test]
+    fn compact_size_rejects_non_minimal_0xfe() {
+        let mut bytes = vec![0xFE];
+        bytes.extend_from_slice(&0xFFFFu32.to_le_bytes());
+        assert_eq!(
+            CompactSize::from_bytes(&bytes),
+            Err(BitcoinError::InvalidFormat)
+        );
+    }
+
+    #[test]
+    fn compact_size_rejects_non_minimal_0xff() {
+        let mut bytes = vec![0xFF];
+        bytes.extend_from_slice(&0xFFFF_FFFFu64.to_le_bytes());
+        assert_eq!(
+            CompactSize::from_bytes(&bytes),
+            Err(BitcoinError::InvalidFormat)
+        );
+    }
+
+    #[test]
+    fn compact_size_accepts_minimal_encodings() {
+        assert_eq!(CompactSize::from_bytes(&[0xFC]).unwrap().0.value, 0xFC);
+
+        let bytes = [0xFD, 0xFD, 0x00];
+        assert_eq!(CompactSize::from_bytes(&bytes).unwrap().0.value, 0xFD);
+
+        let mut bytes = vec![0xFE];
+        bytes.extend_from_slice(&0x10000u32.to_le_bytes());
+        assert_eq!(CompactSize::from_bytes(&bytes).unwrap().0.value, 0x10000);
+
+        let mut bytes = vec![0xFF];
+        bytes.extend_from_slice(&0x1_0000_0000u64.to_le_bytes());
+        assert_eq!(CompactSize::from_bytes(&bytes).unwrap().0.value, 0x1_0000_0000);
+    }
+
+    #[test]
+    fn script_from_bytes_rejects_implausible_length_instead_of_overflowing() {
+        let mut bytes = vec![0xFF];
+        bytes.extend_from_slice(&u64::MAX.to_le_bytes());
+        bytes.extend_from_slice(&[1, 2, 3]);
+        assert_eq!(Script::from_bytes(&bytes), Err(BitcoinError::InvalidFormat));
+    }
+
+    #[test]
+    fn witness_from_bytes_rejects_implausible_item_length_instead_of_overflowing() {
+        let mut bytes = vec![0x01]; // one item
+        bytes.push(0xFF);
+        bytes.extend_from_slice(&u64::MAX.to_le_bytes());
+        assert_eq!(
+            Witness::from_bytes(&bytes),
+            Err(BitcoinError::InvalidFormat)
+        );
+    }
+
+    #[test]
+    fn bitcoin_transaction_rejects_implausible_input_count() {
+        let max_inputs = max_trusted_count(TransactionInput::min_serialized_size()) as u64;
+        let mut bytes = 1u32.to_le_bytes().to_vec();
+        bytes.extend_from_slice(&CompactSize::new(max_inputs + 1).to_bytes());
+        assert_eq!(
+            BitcoinTransaction::from_bytes(&bytes),
+            Err(BitcoinError::InvalidFormat)
+        );
+    }
+
+    fn sample_input() -> TransactionInput {
+        TransactionInput::new(
+            OutPoint::new([0x11; 32], 0),
+            Script::new(vec![0xAB, 0xCD]),
+            0xFFFFFFFF,
+        )
+    }
+
+    fn sample_output() -> TransactionOutput {
+        TransactionOutput::new(50_000, Script::new(vec![0x76, 0xA9]))
+    }
+
+    #[test]
+    fn segwit_transaction_round_trips() {
+        let tx = BitcoinTransaction::new(
+            2,
+            vec![sample_input()],
+            vec![sample_output()],
+            0,
+            vec![vec![vec![0x01, 0x02], vec![0x03]]],
+        );
+
+        let bytes = tx.to_bytes();
+        // Marker/flag bytes must immediately follow the 4-byte version.
+        assert_eq!(&bytes[4..6], &[0x00, 0x01]);
+
+        let (decoded, len) = BitcoinTransaction::from_bytes(&bytes).unwrap();
+        assert_eq!(decoded, tx);
+        assert_eq!(len, bytes.len());
+    }
+
+    #[test]
+    fn legacy_transaction_omits_segwit_marker() {
+        let tx = BitcoinTransaction::new(1, vec![sample_input()], vec![sample_output()], 0, vec![]);
+
+        let bytes = tx.to_bytes();
+        assert_ne!(&bytes[4..6], &[0x00, 0x01]);
+
+        let (decoded, len) = BitcoinTransaction::from_bytes(&bytes).unwrap();
+        assert_eq!(decoded, tx);
+        assert_eq!(len, bytes.len());
+    }
+
+    #[test]
+    fn zero_input_transaction_round_trips_unambiguously() {
+        // A zero-input count is otherwise indistinguishable from the
+        // SegWit marker byte, so this must always serialize with the
+        // marker/flag present even though there is no witness data.
+        let tx = BitcoinTransaction::new(1, vec![], vec![sample_output()], 0, vec![]);
+
+        let bytes = tx.to_bytes();
+        assert_eq!(&bytes[4..6], &[0x00, 0x01]);
+
+        let (decoded, len) = BitcoinTransaction::from_bytes(&bytes).unwrap();
+        assert_eq!(decoded, tx);
+        assert_eq!(len, bytes.len());
+    }
+
+    fn sample_transaction(lock_time: u32) -> BitcoinTransaction {
+        BitcoinTransaction::new(1, vec![sample_input()], vec![sample_output()], lock_time, vec![])
+    }
+
+    #[test]
+    fn merkle_root_matches_independently_computed_vector_for_odd_transaction_count() {
+        let transactions = vec![
+            sample_transaction(0),
+            sample_transaction(1),
+            sample_transaction(2),
+        ];
+
+        // This expected root was computed independently of this crate, by
+        // feeding each transaction's `to_bytes()` hex through Python's
+        // `hashlib` (double-SHA256 each tx, duplicate the last hash to pad
+        // the odd count to 4, then double-SHA256 each pair up to the root),
+        // so a shared bug in `compute_merkle_root` can't make this pass.
+        let expected_root: [u8; 32] =
+            hex::decode("26b54f5d1142f4091416936323bbf77a2266391c57f91eb60198f067a5fbb97d")
+                .unwrap()
+                .try_into()
+                .unwrap();
+
+        let header = BlockHeader::new(1, [0u8; 32], expected_root, 0, 0, 0);
+        let block = Block::new(header, transactions);
+
+        assert_eq!(block.compute_merkle_root(), expected_root);
+        assert!(block.verify_merkle_root());
+    }
+
+    #[test]
+    fn merkle_root_verification_fails_on_mismatch() {
+        let header = BlockHeader::new(1, [0u8; 32], [0xAA; 32], 0, 0, 0);
+        let block = Block::new(header, vec![sample_transaction(0)]);
+        assert!(!block.verify_merkle_root());
+    }
+
+    #[test]
+    fn block_round_trips() {
+        let transactions = vec![sample_transaction(0), sample_transaction(1)];
+        let root = Block::new(
+            BlockHeader::new(1, [0u8; 32], [0u8; 32], 0, 0, 0),
+            transactions.clone(),
+        )
+        .compute_merkle_root();
+        let header = BlockHeader::new(1, [0x22; 32], root, 1_700_000_000, 0x1d00ffff, 42);
+        let block = Block::new(header, transactions);
+
+        let bytes = block.to_bytes();
+        let (decoded, len) = Block::from_bytes(&bytes).unwrap();
+        assert_eq!(decoded, block);
+        assert_eq!(len, bytes.len());
+        assert!(decoded.verify_merkle_root());
+    }
+
+    #[test]
+    fn short_vec_round_trips_single_and_multi_byte_values() {
+        for value in [0u64, 1, 0x7F, 0x80, 0x81, 300, 16_384, u64::MAX] {
+            let encoded = ShortVec::encode(value);
+            let (decoded, len) = ShortVec::decode(&encoded).unwrap();
+            assert_eq!(decoded, value);
+            assert_eq!(len, encoded.len());
+        }
+    }
+
+    #[test]
+    fn short_vec_rejects_non_minimal_trailing_zero_byte() {
+        // 0x80 signals "more bytes follow" with a payload of 0, then a
+        // terminating 0x00 is redundant: 0 is already encoded by the
+        // single byte 0x00.
+        let bytes = [0x80, 0x00];
+        assert_eq!(ShortVec::decode(&bytes), Err(BitcoinError::InvalidFormat));
+    }
+
+    #[test]
+    fn short_vec_rejects_encodings_longer_than_ten_bytes() {
+        let bytes = [0x80u8; 11];
+        assert_eq!(ShortVec::decode(&bytes), Err(BitcoinError::InvalidFormat));
+    }
+
+    #[test]
+    fn script_round_trips_with_short_vec_codec() {
+        let script = Script::new(vec![1, 2, 3, 4, 5]);
+        let bytes = script.to_bytes_with::<ShortVec>();
+        let (decoded, len) = Script::from_bytes_with::<ShortVec>(&bytes).unwrap();
+        assert_eq!(decoded, script);
+        assert_eq!(len, bytes.len());
+    }
+
+    #[test]
+    fn bitcoin_transaction_round_trips_with_short_vec_codec() {
+        let tx = sample_transaction(7);
+        let bytes = tx.to_bytes_with::<ShortVec>();
+        let (decoded, len) = BitcoinTransaction::from_bytes_with::<ShortVec>(&bytes).unwrap();
+        assert_eq!(decoded, tx);
+        assert_eq!(len, bytes.len());
+    }
+}